@@ -0,0 +1,60 @@
+use bytes::{Buf, BufMut, BytesMut};
+use tokio_util::codec::{Decoder, Encoder};
+
+use crate::KafkaError;
+
+/// Frames a raw TCP byte stream into Kafka messages: each message is
+/// prefixed by a 4-byte big-endian `message_size` covering everything
+/// that follows it. Wrapping a socket in `Framed<_, KafkaCodec>` turns it
+/// into a `Stream<Item = BytesMut>` / `Sink<Vec<u8>>`, so a single
+/// connection can pipeline many requests instead of handling just one.
+#[derive(Default)]
+pub struct KafkaCodec;
+
+/// Caps how large a single frame's declared `message_size` is allowed to
+/// be, so a malformed or hostile length prefix can't force an unbounded
+/// (or, for a negative value cast to `usize`, near-`usize::MAX`) buffer
+/// reservation before any of the frame's actual bytes have arrived.
+const MAX_FRAME_SIZE: i32 = 100 * 1024 * 1024;
+
+impl Decoder for KafkaCodec {
+    type Item = BytesMut;
+    type Error = KafkaError;
+
+    fn decode(&mut self, src: &mut BytesMut) -> Result<Option<Self::Item>, Self::Error> {
+        if src.len() < 4 {
+            return Ok(None);
+        }
+
+        let mut size_bytes = [0u8; 4];
+        size_bytes.copy_from_slice(&src[..4]);
+        let message_size = i32::from_be_bytes(size_bytes);
+
+        if !(0..=MAX_FRAME_SIZE).contains(&message_size) {
+            return Err(KafkaError::FrameTooLarge(message_size));
+        }
+        let message_size = message_size as usize;
+
+        if src.len() < 4 + message_size {
+            src.reserve(4 + message_size - src.len());
+            return Ok(None);
+        }
+
+        src.advance(4);
+        Ok(Some(src.split_to(message_size)))
+    }
+}
+
+/// Encodes an already-serialized response (header + body, no length
+/// prefix) by prepending the computed `message_size`.
+impl Encoder<Vec<u8>> for KafkaCodec {
+    type Error = KafkaError;
+
+    fn encode(&mut self, item: Vec<u8>, dst: &mut BytesMut) -> Result<(), Self::Error> {
+        let message_size = item.len() as i32;
+        dst.reserve(4 + item.len());
+        dst.put_i32(message_size);
+        dst.extend_from_slice(&item);
+        Ok(())
+    }
+}
@@ -1,35 +1,74 @@
-use std::{
-    io::{Read, Write},
-    net::{TcpListener, TcpStream},
-};
+mod codec;
+mod protocol;
+#[cfg(feature = "tls")]
+mod tls;
+mod wire;
+
+use std::net::SocketAddr;
+use std::panic::AssertUnwindSafe;
+#[cfg(feature = "tls")]
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use bytes::{Buf, BytesMut};
+use clap::Parser;
+use futures::{FutureExt, SinkExt, StreamExt};
 use thiserror::Error;
-use tracing::{debug, error, info, warn};
+use tokio::io::{AsyncRead, AsyncWrite};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::Semaphore;
+use tokio_util::codec::Framed;
+use tracing::{debug, error, info};
+
+use codec::KafkaCodec;
+use protocol::{handle_request, is_flexible, RequestBody, API_KEY_API_VERSIONS};
+use wire::{read_nullable_string, read_tag_buffer, write_tag_buffer, LengthExceedsBuffer};
 
 #[derive(Error, Debug)]
 enum KafkaError {
     #[error("Failed to read from stream: {0}")]
     ReadError(#[from] std::io::Error),
+    #[error("Malformed request: {0}")]
+    DecodeError(#[from] LengthExceedsBuffer),
+    #[error("Invalid frame size: {0}")]
+    FrameTooLarge(i32),
 }
 
-// TODO: Parse API Version.
-// 1. Diff APIs based on api key key.
-// 2. Diff request bodies for every API.
-// 3. Response code for each event. If writes succeeded - for Produce API.
-// 4. request_api_version in header specify the API version used.
-// 5. API Versions: indicates what versions the broker supports.
+/// Broker startup configuration, overridable via CLI flags or environment
+/// variables so operators can tune it without a rebuild.
+#[derive(Parser, Debug)]
+#[command(name = "kafka", about = "A toy Kafka broker")]
+struct Args {
+    /// Address the broker listens on
+    #[arg(long, env = "KAFKA_BIND_ADDR", default_value = "127.0.0.1:9092")]
+    bind_addr: String,
 
-/// Kafka response message
-struct Response {
-    /// Specifies size of header and body
-    message_size: i32,
-    /// Helps clients match their original requests
-    correlation_id: i32,
-}
+    /// Maximum number of connections handled concurrently; additional
+    /// accepted connections wait for a free slot before being served
+    #[arg(long, env = "KAFKA_MAX_CONNECTIONS", default_value_t = 256)]
+    max_connections: usize,
+
+    /// Path to the server's TLS certificate (PEM). Setting this together
+    /// with `tls_key` enables the TLS listener instead of plaintext.
+    #[cfg(feature = "tls")]
+    #[arg(long, env = "KAFKA_TLS_CERT")]
+    tls_cert: Option<PathBuf>,
+
+    /// Path to the server's TLS private key (PEM)
+    #[cfg(feature = "tls")]
+    #[arg(long, env = "KAFKA_TLS_KEY")]
+    tls_key: Option<PathBuf>,
 
-enum RequestApi {
-    ApiVersions,
+    /// Path to a CA bundle (PEM) used to require and verify client
+    /// certificates (mTLS); leave unset for server-only TLS
+    #[cfg(feature = "tls")]
+    #[arg(long, env = "KAFKA_TLS_CLIENT_CA")]
+    tls_client_ca: Option<PathBuf>,
 }
 
+// TODO:
+// 1. Response code for each event. If writes succeeded - for Produce API.
+
 /// Kafka request message
 #[derive(bon::Builder)]
 struct Request {
@@ -43,93 +82,264 @@ struct Request {
     correlation_id: i32,
     /// The client ID for the request
     client_id: Option<String>,
-    /// Optional tagged fields
+    /// Tagged fields (`tag`, raw bytes), present once `request_api_version`
+    /// crosses the request's flexible-version threshold
     #[builder(default)]
-    tag_buffer: Vec<String>,
+    tag_buffer: Vec<(u32, Vec<u8>)>,
 }
 
 impl Request {
-    fn from(stream: &mut TcpStream) -> Result<Self, KafkaError> {
-        // Read the message size (4 bytes)
-        let mut size_bytes = [0u8; 4];
-        stream.read_exact(&mut size_bytes)?;
-        let message_size = i32::from_be_bytes(size_bytes);
+    /// Parses the request header out of one already-framed message (the
+    /// 4-byte `message_size` prefix has already been stripped by
+    /// `KafkaCodec`). Leaves `buf` positioned at the start of the body.
+    ///
+    /// `client_id` is always a classic (non-compact) nullable string, even
+    /// for flexible API versions — only the trailing tagged fields buffer
+    /// is new in the flexible header, which is why its presence is decided
+    /// by `protocol::is_flexible` rather than by the string encoding.
+    fn decode(buf: &mut BytesMut) -> Result<Self, KafkaError> {
+        let message_size = buf.len() as i32;
         debug!("Message size: {}", message_size);
 
-        // Read the API key (2 bytes)
-        let mut api_key_bytes = [0u8, 2];
-        stream.read_exact(&mut api_key_bytes)?;
-        let request_api_key = u16::from_be_bytes(api_key_bytes);
+        let request_api_key = buf.get_u16();
         debug!("Request API key: {}", request_api_key);
 
-        // Read the API version (2 bytes)
-        let mut api_version_bytes = [0u8; 2];
-        stream.read_exact(&mut api_version_bytes)?;
-        let request_api_version = u16::from_be_bytes(api_version_bytes);
+        let request_api_version = buf.get_u16();
         debug!("Request API version: {}", request_api_version);
 
-        // Read correlation ID (4 bytes)
-        let mut correlation_bytes = [0u8; 4];
-        stream.read_exact(&mut correlation_bytes)?;
-        let correlation_id = i32::from_be_bytes(correlation_bytes);
+        let correlation_id = buf.get_i32();
         debug!("Correlation ID: {}", correlation_id);
 
+        let client_id = read_nullable_string(buf)?;
+        debug!("Client ID: {:?}", client_id);
+
+        let tag_buffer = if is_flexible(request_api_key, request_api_version) {
+            read_tag_buffer(buf)?
+        } else {
+            Vec::new()
+        };
+
         Ok(Request::builder()
             .message_size(message_size)
             .request_api_key(request_api_key)
             .request_api_version(request_api_version)
             .correlation_id(correlation_id)
+            .maybe_client_id(client_id)
+            .tag_buffer(tag_buffer)
             .build())
     }
 }
 
-fn main() -> Result<(), KafkaError> {
+/// Whether the response header for this request carries a trailing tagged
+/// fields buffer (header v1) or is correlation_id-only (header v0).
+/// ApiVersions is a special case: its response header stays v0 even once
+/// the request itself negotiates a flexible version, since a client can't
+/// know the broker's supported flexible version until after this exchange
+/// completes. Every other API follows the request body's own flexibility.
+fn response_header_is_flexible(request: &Request) -> bool {
+    request.request_api_key != API_KEY_API_VERSIONS
+        && is_flexible(request.request_api_key, request.request_api_version)
+}
+
+/// Builds the full response frame (header + body, no length prefix — the
+/// codec adds that) for a single request. `body_buf` holds whatever of the
+/// frame is left after the header has been parsed out of it.
+fn build_response_bytes(request: &Request, body_buf: &mut BytesMut) -> Result<Vec<u8>, KafkaError> {
+    let request_body = RequestBody::decode(
+        request.request_api_key,
+        request.request_api_version,
+        body_buf,
+    )?;
+    let response_body = handle_request(&request_body, request.request_api_version);
+
+    let mut response = Vec::new();
+    response.extend_from_slice(&request.correlation_id.to_be_bytes());
+    if response_header_is_flexible(request) {
+        write_tag_buffer(&mut response, &[]);
+    }
+    response_body.encode(request.request_api_version, &mut response);
+    Ok(response)
+}
+
+/// Extracts a human-readable message out of a caught panic payload, falling
+/// back to a generic description for payloads that aren't `&str`/`String`.
+fn panic_message(panic: &(dyn std::any::Any + Send)) -> String {
+    if let Some(s) = panic.downcast_ref::<&str>() {
+        s.to_string()
+    } else if let Some(s) = panic.downcast_ref::<String>() {
+        s.clone()
+    } else {
+        "unknown panic payload".to_string()
+    }
+}
+
+/// Drives a single connection's framed stream until the client disconnects
+/// or a request fails to parse, handling every pipelined request on it.
+/// Generic over the transport so the same pipeline runs over a plain
+/// `TcpStream` or a `rustls` TLS stream wrapping one.
+async fn handle_connection<S>(stream: S)
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    let mut framed = Framed::new(stream, KafkaCodec);
+
+    while let Some(frame) = framed.next().await {
+        let mut frame = match frame {
+            Ok(frame) => frame,
+            Err(err) => {
+                error!("Could not read frame: {}", err);
+                return;
+            }
+        };
+
+        let request = match Request::decode(&mut frame) {
+            Ok(request) => request,
+            Err(err) => {
+                error!("Could not parse request: {}", err);
+                return;
+            }
+        };
+
+        info!(correlation_id = request.correlation_id, "Received request");
+
+        let response = match build_response_bytes(&request, &mut frame) {
+            Ok(response) => response,
+            Err(err) => {
+                error!("Could not build response: {}", err);
+                return;
+            }
+        };
+        if let Err(err) = framed.send(response).await {
+            error!("Failed to send response: {}", err);
+            return;
+        }
+    }
+}
+
+/// Runs `handle_connection` with panics confined to this connection's task
+/// and logged rather than taking down the listener.
+async fn serve<S>(stream: S, addr: SocketAddr)
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    if let Err(panic) = AssertUnwindSafe(handle_connection(stream)).catch_unwind().await {
+        error!(%addr, message = %panic_message(&panic), "Connection task panicked");
+    }
+}
+
+#[tokio::main]
+async fn main() -> Result<(), KafkaError> {
     // Initialize tracing subscriber with RUST_LOG env var, defaulting to "info"
     tracing_subscriber::fmt()
         .with_env_filter(std::env::var("RUST_LOG").unwrap_or_else(|_| "info".into()))
         .init();
 
+    let args = Args::parse();
+
     info!("Starting Kafka server...");
 
-    let listener = TcpListener::bind("127.0.0.1:9092").unwrap();
-    info!("Listening on 127.0.0.1:9092");
+    let listener = TcpListener::bind(&args.bind_addr).await.unwrap();
+    info!(addr = %args.bind_addr, max_connections = args.max_connections, "Listening");
 
-    for stream in listener.incoming() {
-        match stream {
-            Ok(mut stream) => {
-                info!("Accepted new connection");
+    // Bounds how many connections are served at once: once `max_connections`
+    // permits are checked out, further accepted sockets wait for one to free
+    // up instead of spawning unboundedly.
+    let connection_slots = Arc::new(Semaphore::new(args.max_connections));
 
-                let request = match Request::from(&mut stream) {
-                    Ok(request) => request,
-                    Err(err) => {
-                        error!("Could not parse request: {}", err);
-                        continue;
-                    }
-                };
+    #[cfg(feature = "tls")]
+    let tls_acceptor = tls::build_acceptor(&args);
+    #[cfg(feature = "tls")]
+    info!(tls_enabled = tls_acceptor.is_some(), "TLS configuration loaded");
 
-                info!(correlation_id = request.correlation_id, "Received request");
+    loop {
+        match listener.accept().await {
+            Ok((stream, addr)) => {
+                let permit = connection_slots.clone().acquire_owned().await.unwrap();
+                info!(%addr, "Accepted new connection");
 
-                let response = Response {
-                    message_size: 0,
-                    correlation_id: request.correlation_id,
-                };
+                #[cfg(feature = "tls")]
+                let tls_acceptor = tls_acceptor.clone();
 
-                let size_bytes = response.message_size.to_be_bytes();
-                let correlation_bytes = response.correlation_id.to_be_bytes();
+                tokio::spawn(async move {
+                    let _permit = permit;
 
-                // Write the response to the stream
-                stream.write_all(&size_bytes).unwrap();
-                stream.write_all(&correlation_bytes).unwrap();
+                    #[cfg(feature = "tls")]
+                    if let Some(acceptor) = tls_acceptor {
+                        match acceptor.accept(stream).await {
+                            Ok(tls_stream) => {
+                                log_tls_session(&addr, &tls_stream);
+                                serve(tls_stream, addr).await;
+                            }
+                            Err(err) => error!(%addr, "TLS handshake failed: {}", err),
+                        }
+                        return;
+                    }
 
-                if let Err(e) = stream.flush() {
-                    error!("Failed to flush stream: {}", e);
-                }
+                    serve(stream, addr).await;
+                });
             }
             Err(e) => {
                 error!("Connection error: {}", e);
             }
         }
     }
+}
+
+/// Logs the negotiated TLS protocol version and cipher suite once a
+/// handshake with a client completes.
+#[cfg(feature = "tls")]
+fn log_tls_session(addr: &SocketAddr, stream: &tokio_rustls::server::TlsStream<TcpStream>) {
+    let (_, session) = stream.get_ref();
+    let version = session.protocol_version();
+    let cipher = session.negotiated_cipher_suite().map(|c| c.suite());
+    info!(%addr, ?version, ?cipher, "TLS handshake complete");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-    Ok(())
+    /// ApiVersions v0 request: classic header, no tagged fields buffer.
+    #[test]
+    fn decode_classic_header_v0_api_versions() {
+        let mut buf = BytesMut::from(
+            &[
+                0x00, 0x12, // request_api_key = 18 (ApiVersions)
+                0x00, 0x00, // request_api_version = 0
+                0x00, 0x00, 0x00, 0x2a, // correlation_id = 42
+                0x00, 0x04, b'k', b'a', b'f', b'k', // client_id = "kafk" (len 4)
+            ][..],
+        );
+        let request = Request::decode(&mut buf).unwrap();
+
+        assert_eq!(request.request_api_key, 18);
+        assert_eq!(request.request_api_version, 0);
+        assert_eq!(request.correlation_id, 42);
+        assert_eq!(request.client_id.as_deref(), Some("kafk"));
+        assert!(request.tag_buffer.is_empty());
+        assert!(buf.is_empty());
+    }
+
+    /// ApiVersions v3 request: flexible header, so a tagged fields buffer
+    /// follows `client_id` even though `client_id` itself stays classic.
+    #[test]
+    fn decode_flexible_header_v3_api_versions() {
+        let mut buf = BytesMut::from(
+            &[
+                0x00, 0x12, // request_api_key = 18 (ApiVersions)
+                0x00, 0x03, // request_api_version = 3
+                0x00, 0x00, 0x00, 0x07, // correlation_id = 7
+                0xff, 0xff, // client_id = null
+                0x00, // tag_buffer: 0 entries
+            ][..],
+        );
+        let request = Request::decode(&mut buf).unwrap();
+
+        assert_eq!(request.request_api_key, 18);
+        assert_eq!(request.request_api_version, 3);
+        assert_eq!(request.correlation_id, 7);
+        assert_eq!(request.client_id, None);
+        assert!(request.tag_buffer.is_empty());
+        assert!(buf.is_empty());
+    }
 }
@@ -0,0 +1,668 @@
+use bytes::{Buf, BufMut};
+
+use crate::wire::{
+    read_compact_array_len, read_compact_string, read_nullable_string, read_tag_buffer,
+    write_compact_array_len, write_compact_string, write_nullable_string, write_tag_buffer,
+    LengthExceedsBuffer,
+};
+
+/// API keys this file knows how to decode/encode a typed body for.
+pub const API_KEY_PRODUCE: u16 = 0;
+pub const API_KEY_FETCH: u16 = 1;
+pub const API_KEY_METADATA: u16 = 3;
+pub const API_KEY_FIND_COORDINATOR: u16 = 10;
+pub const API_KEY_API_VERSIONS: u16 = 18;
+
+const ERROR_NONE: i16 = 0;
+const ERROR_COORDINATOR_NOT_AVAILABLE: i16 = 15;
+const ERROR_UNSUPPORTED_VERSION: i16 = 35;
+
+/// The request version at which each API's body switches to the flexible
+/// (KIP-482) wire format — compact strings/arrays plus a trailing tagged
+/// fields buffer. Below this version, the body uses classic encoding.
+const FLEXIBLE_SINCE: &[(u16, u16)] = &[
+    (API_KEY_PRODUCE, 9),
+    (API_KEY_FETCH, 12),
+    (API_KEY_METADATA, 9),
+    (API_KEY_FIND_COORDINATOR, 3),
+    (API_KEY_API_VERSIONS, 3),
+];
+
+/// Whether `api_key` at `api_version` uses the flexible wire format. This
+/// also governs whether the request header carries a trailing tagged
+/// fields buffer after `client_id`.
+pub fn is_flexible(api_key: u16, api_version: u16) -> bool {
+    FLEXIBLE_SINCE
+        .iter()
+        .any(|&(key, since)| key == api_key && api_version >= since)
+}
+
+/// A single `(api_key, min_version, max_version)` entry advertised by the
+/// broker's ApiVersions response.
+pub struct ApiVersion {
+    pub api_key: i16,
+    pub min_version: i16,
+    pub max_version: i16,
+}
+
+/// The APIs this broker supports, and the version range it accepts for each.
+/// `max_version` for each tracks the highest version this file's decode/encode
+/// path actually handles byte-for-byte, which for Produce/Fetch/Metadata/
+/// FindCoordinator is the version at which they turn flexible
+/// (`FLEXIBLE_SINCE`) — later versions add fields beyond what's modeled
+/// here. Every version-gated field between 0 and that max (e.g. Metadata's
+/// `controller_id`/`cluster_id`, FindCoordinator's `throttle_time_ms`, Fetch's
+/// `error_code`/`session_id`) must actually be encoded for the version it's
+/// introduced at — an always-present or always-absent field silently
+/// desyncs every client on the wrong side of that version. Extend this
+/// table as more APIs, or higher versions of these, are implemented, and
+/// keep the corresponding `encode` in step with whatever `max_version`
+/// claims.
+pub const SUPPORTED_APIS: &[ApiVersion] = &[
+    ApiVersion {
+        api_key: API_KEY_PRODUCE as i16,
+        min_version: 0,
+        max_version: 9,
+    },
+    ApiVersion {
+        api_key: API_KEY_FETCH as i16,
+        min_version: 0,
+        max_version: 12,
+    },
+    ApiVersion {
+        api_key: API_KEY_METADATA as i16,
+        min_version: 0,
+        max_version: 9,
+    },
+    ApiVersion {
+        api_key: API_KEY_FIND_COORDINATOR as i16,
+        min_version: 0,
+        max_version: 3,
+    },
+    ApiVersion {
+        api_key: API_KEY_API_VERSIONS as i16,
+        min_version: 0,
+        max_version: 4,
+    },
+];
+
+/// ApiVersions (key 18) request. Versions 0-2 carry no fields; v3+ adds a
+/// client_software_name/version pair plus a trailing tagged fields buffer.
+pub struct ApiVersionsRequest {
+    pub client_software_name: Option<String>,
+    pub client_software_version: Option<String>,
+}
+
+impl ApiVersionsRequest {
+    pub fn decode(version: u16, buf: &mut impl Buf) -> Result<Self, LengthExceedsBuffer> {
+        if !is_flexible(API_KEY_API_VERSIONS, version) {
+            return Ok(ApiVersionsRequest {
+                client_software_name: None,
+                client_software_version: None,
+            });
+        }
+
+        let client_software_name = read_compact_string(buf)?;
+        let client_software_version = read_compact_string(buf)?;
+        read_tag_buffer(buf)?;
+
+        Ok(ApiVersionsRequest {
+            client_software_name,
+            client_software_version,
+        })
+    }
+}
+
+pub struct ApiVersionsResponse {
+    pub error_code: i16,
+    pub throttle_time_ms: i32,
+}
+
+impl ApiVersionsResponse {
+    pub fn for_request(version: u16) -> Self {
+        let error_code = if version > 4 {
+            ERROR_UNSUPPORTED_VERSION
+        } else {
+            ERROR_NONE
+        };
+        ApiVersionsResponse {
+            error_code,
+            throttle_time_ms: 0,
+        }
+    }
+
+    /// Encodes the body. v3+ uses a COMPACT_ARRAY of api_keys (each entry
+    /// followed by its own empty tagged fields buffer) and a trailing
+    /// top-level tagged fields buffer; v0-v2 use the classic array form
+    /// and have no tagged fields at all.
+    ///
+    /// An out-of-range version always gets the classic (v0) layout
+    /// regardless of `version` itself, the same reasoning
+    /// `response_header_is_flexible` applies to the header: a client whose
+    /// requested version we just rejected can't be assumed to understand
+    /// any flexible format, so replying in one would be unparseable to it.
+    pub fn encode(&self, version: u16, buf: &mut impl BufMut) {
+        let flexible =
+            self.error_code != ERROR_UNSUPPORTED_VERSION && is_flexible(API_KEY_API_VERSIONS, version);
+
+        buf.put_i16(self.error_code);
+
+        if flexible {
+            write_compact_array_len(buf, SUPPORTED_APIS.len());
+        } else {
+            buf.put_i32(SUPPORTED_APIS.len() as i32);
+        }
+        for api in SUPPORTED_APIS {
+            buf.put_i16(api.api_key);
+            buf.put_i16(api.min_version);
+            buf.put_i16(api.max_version);
+            if flexible {
+                write_tag_buffer(buf, &[]);
+            }
+        }
+
+        buf.put_i32(self.throttle_time_ms);
+        if flexible {
+            write_tag_buffer(buf, &[]);
+        }
+    }
+}
+
+/// Produce (key 0). Per-topic/partition record-batch payloads are carried
+/// through opaquely until Produce semantics (actually appending records)
+/// are implemented.
+pub struct ProduceRequest {
+    pub transactional_id: Option<String>,
+    pub acks: i16,
+    pub timeout_ms: i32,
+    pub topics_raw: Vec<u8>,
+}
+
+impl ProduceRequest {
+    /// `topics_raw` swallows whatever is left of the body verbatim, so it
+    /// transparently carries a v9+ trailing tagged fields buffer along with
+    /// the topics themselves — only `transactional_id`, which precedes that
+    /// opaque tail, needs to pick its string encoding based on `version`.
+    pub fn decode(version: u16, buf: &mut impl Buf) -> Result<Self, LengthExceedsBuffer> {
+        let transactional_id = if version >= 3 {
+            if is_flexible(API_KEY_PRODUCE, version) {
+                read_compact_string(buf)?
+            } else {
+                read_nullable_string(buf)?
+            }
+        } else {
+            None
+        };
+        let acks = buf.get_i16();
+        let timeout_ms = buf.get_i32();
+        let mut topics_raw = vec![0u8; buf.remaining()];
+        buf.copy_to_slice(&mut topics_raw);
+
+        Ok(ProduceRequest {
+            transactional_id,
+            acks,
+            timeout_ms,
+            topics_raw,
+        })
+    }
+}
+
+/// No topics were actually appended yet, so the responses array is empty.
+pub struct ProduceResponse {
+    pub throttle_time_ms: i32,
+}
+
+impl ProduceResponse {
+    pub fn encode(&self, version: u16, buf: &mut impl BufMut) {
+        let flexible = is_flexible(API_KEY_PRODUCE, version);
+        if flexible {
+            write_compact_array_len(buf, 0); // responses: empty array
+        } else {
+            buf.put_i32(0);
+        }
+        buf.put_i32(self.throttle_time_ms);
+        if flexible {
+            write_tag_buffer(buf, &[]);
+        }
+    }
+}
+
+/// Fetch (key 1). Topic/partition fetch requests are carried through
+/// opaquely until Fetch semantics are implemented.
+pub struct FetchRequest {
+    pub replica_id: i32,
+    pub max_wait_ms: i32,
+    pub min_bytes: i32,
+    pub topics_raw: Vec<u8>,
+}
+
+impl FetchRequest {
+    /// None of the fields read here are strings or arrays, so nothing
+    /// changes shape at the v12 flexible threshold; `topics_raw` still
+    /// swallows the (opaque) rest of the body, trailing tagged fields
+    /// buffer included. `version` is threaded through anyway so callers
+    /// stay consistent with the other request bodies.
+    pub fn decode(_version: u16, buf: &mut impl Buf) -> Result<Self, LengthExceedsBuffer> {
+        let replica_id = buf.get_i32();
+        let max_wait_ms = buf.get_i32();
+        let min_bytes = buf.get_i32();
+        let mut topics_raw = vec![0u8; buf.remaining()];
+        buf.copy_to_slice(&mut topics_raw);
+
+        Ok(FetchRequest {
+            replica_id,
+            max_wait_ms,
+            min_bytes,
+            topics_raw,
+        })
+    }
+}
+
+pub struct FetchResponse {
+    pub throttle_time_ms: i32,
+}
+
+impl FetchResponse {
+    /// v7+ inserts `error_code`/`session_id` between `throttle_time_ms` and
+    /// `responses`; skipping them (rather than gating by version) would
+    /// shift the `responses` array length itself, not just trailing zeroed
+    /// fields.
+    pub fn encode(&self, version: u16, buf: &mut impl BufMut) {
+        let flexible = is_flexible(API_KEY_FETCH, version);
+        buf.put_i32(self.throttle_time_ms);
+        if version >= 7 {
+            buf.put_i16(ERROR_NONE);
+            buf.put_i32(0); // session_id
+        }
+        if flexible {
+            write_compact_array_len(buf, 0); // responses: empty array
+            write_tag_buffer(buf, &[]);
+        } else {
+            buf.put_i32(0);
+        }
+    }
+}
+
+/// Metadata (key 3). A `None` topic list means "all topics", matching the
+/// wire convention of a null array.
+pub struct MetadataRequest {
+    pub topics: Option<Vec<String>>,
+}
+
+impl MetadataRequest {
+    pub fn decode(version: u16, buf: &mut impl Buf) -> Result<Self, LengthExceedsBuffer> {
+        let topics = if is_flexible(API_KEY_METADATA, version) {
+            match read_compact_array_len(buf) {
+                None => None,
+                Some(topic_count) => {
+                    let mut names = Vec::with_capacity(topic_count.min(buf.remaining()));
+                    for _ in 0..topic_count {
+                        names.push(read_compact_string(buf)?.unwrap_or_default());
+                    }
+                    Some(names)
+                }
+            }
+        } else {
+            let topic_count = buf.get_i32();
+            if topic_count < 0 {
+                None
+            } else {
+                let mut names = Vec::with_capacity((topic_count as usize).min(buf.remaining()));
+                for _ in 0..topic_count {
+                    names.push(read_nullable_string(buf)?.unwrap_or_default());
+                }
+                Some(names)
+            }
+        };
+        Ok(MetadataRequest { topics })
+    }
+}
+
+/// No brokers/topics are tracked yet, so every array comes back empty.
+pub struct MetadataResponse {
+    pub throttle_time_ms: i32,
+    pub controller_id: i32,
+}
+
+impl MetadataResponse {
+    /// `cluster_id` and `controller_id` don't exist before v2/v1 respectively,
+    /// and `throttle_time_ms` is a v3+ addition, so each is only written once
+    /// the request version actually carries it — getting this wrong shifts
+    /// every byte after it, not just the missing field's own bytes.
+    pub fn encode(&self, version: u16, buf: &mut impl BufMut) {
+        let flexible = is_flexible(API_KEY_METADATA, version);
+        if version >= 3 {
+            buf.put_i32(self.throttle_time_ms);
+        }
+        if flexible {
+            write_compact_array_len(buf, 0); // brokers: empty array
+        } else {
+            buf.put_i32(0);
+        }
+        if version >= 2 {
+            if flexible {
+                write_compact_string(buf, &None); // cluster_id
+            } else {
+                write_nullable_string(buf, &None);
+            }
+        }
+        if version >= 1 {
+            buf.put_i32(self.controller_id);
+        }
+        if flexible {
+            write_compact_array_len(buf, 0); // topics: empty array
+            write_tag_buffer(buf, &[]);
+        } else {
+            buf.put_i32(0);
+        }
+    }
+}
+
+/// FindCoordinator (key 10), v0-v3 schema: a single coordinator key and
+/// its type (group vs. transaction coordinator), in classic or (v3)
+/// flexible encoding.
+pub struct FindCoordinatorRequest {
+    pub key: Option<String>,
+    pub key_type: i8,
+}
+
+impl FindCoordinatorRequest {
+    pub fn decode(version: u16, buf: &mut impl Buf) -> Result<Self, LengthExceedsBuffer> {
+        let flexible = is_flexible(API_KEY_FIND_COORDINATOR, version);
+        let key = if flexible {
+            read_compact_string(buf)?
+        } else {
+            read_nullable_string(buf)?
+        };
+        let key_type = if version >= 1 { buf.get_i8() } else { 0 };
+        if flexible {
+            read_tag_buffer(buf)?;
+        }
+        Ok(FindCoordinatorRequest { key, key_type })
+    }
+}
+
+/// No coordinator is elected yet, so this always reports "not available".
+pub struct FindCoordinatorResponse {
+    pub throttle_time_ms: i32,
+    pub error_code: i16,
+    pub node_id: i32,
+    pub host: Option<String>,
+    pub port: i32,
+}
+
+impl FindCoordinatorResponse {
+    /// `throttle_time_ms` and `error_message` are both v1+ additions ahead of
+    /// `node_id` in the wire order; omitting them (rather than gating by
+    /// version like the request side already does) desyncs every v1-v3
+    /// client starting from `node_id` itself. `error_message` isn't tracked
+    /// as its own field, so it's always written as null, the same way
+    /// `cluster_id` is in `MetadataResponse`.
+    pub fn encode(&self, version: u16, buf: &mut impl BufMut) {
+        let flexible = is_flexible(API_KEY_FIND_COORDINATOR, version);
+        if version >= 1 {
+            buf.put_i32(self.throttle_time_ms);
+        }
+        buf.put_i16(self.error_code);
+        if version >= 1 {
+            if flexible {
+                write_compact_string(buf, &None); // error_message
+            } else {
+                write_nullable_string(buf, &None);
+            }
+        }
+        buf.put_i32(self.node_id);
+        if flexible {
+            write_compact_string(buf, &self.host);
+        } else {
+            write_nullable_string(buf, &self.host);
+        }
+        buf.put_i32(self.port);
+        if flexible {
+            write_tag_buffer(buf, &[]);
+        }
+    }
+}
+
+/// A decoded request body, dispatched on `request_api_key`. Unrecognized
+/// keys round-trip as opaque bytes so the broker doesn't have to understand
+/// every API to avoid desyncing the connection.
+pub enum RequestBody {
+    Produce(ProduceRequest),
+    Fetch(FetchRequest),
+    Metadata(MetadataRequest),
+    FindCoordinator(FindCoordinatorRequest),
+    ApiVersions(ApiVersionsRequest),
+    Unknown { api_key: u16, raw: Vec<u8> },
+}
+
+impl RequestBody {
+    pub fn decode(api_key: u16, version: u16, buf: &mut impl Buf) -> Result<Self, LengthExceedsBuffer> {
+        Ok(match api_key {
+            API_KEY_PRODUCE => RequestBody::Produce(ProduceRequest::decode(version, buf)?),
+            API_KEY_FETCH => RequestBody::Fetch(FetchRequest::decode(version, buf)?),
+            API_KEY_METADATA => RequestBody::Metadata(MetadataRequest::decode(version, buf)?),
+            API_KEY_FIND_COORDINATOR => {
+                RequestBody::FindCoordinator(FindCoordinatorRequest::decode(version, buf)?)
+            }
+            API_KEY_API_VERSIONS => {
+                RequestBody::ApiVersions(ApiVersionsRequest::decode(version, buf)?)
+            }
+            other => {
+                let mut raw = vec![0u8; buf.remaining()];
+                buf.copy_to_slice(&mut raw);
+                RequestBody::Unknown {
+                    api_key: other,
+                    raw,
+                }
+            }
+        })
+    }
+}
+
+/// The response body matching a decoded `RequestBody`. `Unknown` bodies
+/// produce no response payload beyond the header.
+pub enum ResponseBody {
+    Produce(ProduceResponse),
+    Fetch(FetchResponse),
+    Metadata(MetadataResponse),
+    FindCoordinator(FindCoordinatorResponse),
+    ApiVersions(ApiVersionsResponse),
+    Unknown,
+}
+
+impl ResponseBody {
+    pub fn encode(&self, version: u16, buf: &mut impl BufMut) {
+        match self {
+            ResponseBody::Produce(r) => r.encode(version, buf),
+            ResponseBody::Fetch(r) => r.encode(version, buf),
+            ResponseBody::Metadata(r) => r.encode(version, buf),
+            ResponseBody::FindCoordinator(r) => r.encode(version, buf),
+            ResponseBody::ApiVersions(r) => r.encode(version, buf),
+            ResponseBody::Unknown => {}
+        }
+    }
+}
+
+/// Produces the response body for a decoded request body. This is the
+/// broker's dispatch point for per-API semantics; most APIs here only
+/// report "nothing to report" until their real behavior is implemented.
+pub fn handle_request(body: &RequestBody, version: u16) -> ResponseBody {
+    match body {
+        RequestBody::Produce(_) => ResponseBody::Produce(ProduceResponse {
+            throttle_time_ms: 0,
+        }),
+        RequestBody::Fetch(_) => ResponseBody::Fetch(FetchResponse {
+            throttle_time_ms: 0,
+        }),
+        RequestBody::Metadata(_) => ResponseBody::Metadata(MetadataResponse {
+            throttle_time_ms: 0,
+            controller_id: -1,
+        }),
+        RequestBody::FindCoordinator(_) => {
+            ResponseBody::FindCoordinator(FindCoordinatorResponse {
+                throttle_time_ms: 0,
+                error_code: ERROR_COORDINATOR_NOT_AVAILABLE,
+                node_id: -1,
+                host: None,
+                port: -1,
+            })
+        }
+        RequestBody::ApiVersions(_) => {
+            ResponseBody::ApiVersions(ApiVersionsResponse::for_request(version))
+        }
+        RequestBody::Unknown { .. } => ResponseBody::Unknown,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bytes::BytesMut;
+
+    #[test]
+    fn metadata_request_decode_classic_v8_null_topics() {
+        let mut buf = BytesMut::from(&[0xff, 0xff, 0xff, 0xff][..]); // topic_count = -1
+        let request = MetadataRequest::decode(8, &mut buf).unwrap();
+        assert_eq!(request.topics, None);
+        assert!(buf.is_empty());
+    }
+
+    #[test]
+    fn metadata_request_decode_flexible_v9_one_topic() {
+        let mut buf = BytesMut::new();
+        write_compact_array_len(&mut buf, 1);
+        write_compact_string(&mut buf, &Some("orders".to_string()));
+        let request = MetadataRequest::decode(9, &mut buf).unwrap();
+        assert_eq!(request.topics, Some(vec!["orders".to_string()]));
+        assert!(buf.is_empty());
+    }
+
+    #[test]
+    fn metadata_response_encode_classic_v0_omits_versioned_fields() {
+        let response = MetadataResponse {
+            throttle_time_ms: 7,
+            controller_id: 3,
+        };
+        let mut buf = Vec::new();
+        response.encode(0, &mut buf);
+        // v0 has neither throttle_time_ms, cluster_id nor controller_id.
+        assert_eq!(buf, vec![0, 0, 0, 0, 0, 0, 0, 0]);
+    }
+
+    #[test]
+    fn metadata_response_encode_flexible_v9_includes_all_versioned_fields() {
+        let response = MetadataResponse {
+            throttle_time_ms: 7,
+            controller_id: 3,
+        };
+        let mut buf = Vec::new();
+        response.encode(9, &mut buf);
+
+        let mut expected = Vec::new();
+        expected.extend_from_slice(&7i32.to_be_bytes()); // throttle_time_ms
+        write_compact_array_len(&mut expected, 0); // brokers
+        write_compact_string(&mut expected, &None); // cluster_id
+        expected.extend_from_slice(&3i32.to_be_bytes()); // controller_id
+        write_compact_array_len(&mut expected, 0); // topics
+        write_tag_buffer(&mut expected, &[]);
+        assert_eq!(buf, expected);
+    }
+
+    #[test]
+    fn fetch_response_encode_classic_v11_includes_error_code_and_session_id() {
+        let response = FetchResponse { throttle_time_ms: 5 };
+        let mut buf = Vec::new();
+        response.encode(11, &mut buf);
+
+        let mut expected = Vec::new();
+        expected.extend_from_slice(&5i32.to_be_bytes()); // throttle_time_ms
+        expected.extend_from_slice(&ERROR_NONE.to_be_bytes()); // error_code
+        expected.extend_from_slice(&0i32.to_be_bytes()); // session_id
+        expected.extend_from_slice(&0i32.to_be_bytes()); // responses: empty array
+        assert_eq!(buf, expected);
+    }
+
+    #[test]
+    fn fetch_response_encode_flexible_v12_includes_error_code_session_id_and_tag_buffer() {
+        let response = FetchResponse { throttle_time_ms: 5 };
+        let mut buf = Vec::new();
+        response.encode(12, &mut buf);
+
+        let mut expected = Vec::new();
+        expected.extend_from_slice(&5i32.to_be_bytes()); // throttle_time_ms
+        expected.extend_from_slice(&ERROR_NONE.to_be_bytes()); // error_code
+        expected.extend_from_slice(&0i32.to_be_bytes()); // session_id
+        write_compact_array_len(&mut expected, 0); // responses: empty array
+        write_tag_buffer(&mut expected, &[]);
+        assert_eq!(buf, expected);
+    }
+
+    #[test]
+    fn find_coordinator_request_decode_classic_v2() {
+        let mut buf = BytesMut::new();
+        write_nullable_string(&mut buf, &Some("my-group".to_string()));
+        buf.extend_from_slice(&[0x01]); // key_type = 1 (transaction coordinator)
+        let request = FindCoordinatorRequest::decode(2, &mut buf).unwrap();
+        assert_eq!(request.key.as_deref(), Some("my-group"));
+        assert_eq!(request.key_type, 1);
+        assert!(buf.is_empty());
+    }
+
+    #[test]
+    fn find_coordinator_request_decode_flexible_v3() {
+        let mut buf = BytesMut::new();
+        write_compact_string(&mut buf, &Some("my-group".to_string()));
+        buf.extend_from_slice(&[0x01]); // key_type = 1
+        write_tag_buffer(&mut buf, &[]);
+        let request = FindCoordinatorRequest::decode(3, &mut buf).unwrap();
+        assert_eq!(request.key.as_deref(), Some("my-group"));
+        assert_eq!(request.key_type, 1);
+        assert!(buf.is_empty());
+    }
+
+    #[test]
+    fn find_coordinator_response_encode_classic_v2_includes_throttle_and_error_message() {
+        let response = FindCoordinatorResponse {
+            throttle_time_ms: 9,
+            error_code: ERROR_NONE,
+            node_id: -1,
+            host: None,
+            port: -1,
+        };
+        let mut buf = Vec::new();
+        response.encode(2, &mut buf);
+
+        let mut expected = Vec::new();
+        expected.extend_from_slice(&9i32.to_be_bytes()); // throttle_time_ms
+        expected.extend_from_slice(&ERROR_NONE.to_be_bytes());
+        write_nullable_string(&mut expected, &None); // error_message
+        expected.extend_from_slice(&(-1i32).to_be_bytes()); // node_id
+        write_nullable_string(&mut expected, &None); // host
+        expected.extend_from_slice(&(-1i32).to_be_bytes()); // port
+        assert_eq!(buf, expected);
+    }
+
+    #[test]
+    fn find_coordinator_response_encode_flexible_v3_includes_tag_buffer() {
+        let response = FindCoordinatorResponse {
+            throttle_time_ms: 9,
+            error_code: ERROR_NONE,
+            node_id: -1,
+            host: None,
+            port: -1,
+        };
+        let mut buf = Vec::new();
+        response.encode(3, &mut buf);
+
+        let mut expected = Vec::new();
+        expected.extend_from_slice(&9i32.to_be_bytes());
+        expected.extend_from_slice(&ERROR_NONE.to_be_bytes());
+        write_compact_string(&mut expected, &None); // error_message
+        expected.extend_from_slice(&(-1i32).to_be_bytes());
+        write_compact_string(&mut expected, &None); // host
+        expected.extend_from_slice(&(-1i32).to_be_bytes());
+        write_tag_buffer(&mut expected, &[]);
+        assert_eq!(buf, expected);
+    }
+}
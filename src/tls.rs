@@ -0,0 +1,68 @@
+//! Optional TLS listener (the `tls` feature). When a certificate and key
+//! are configured, accepted sockets are wrapped in a `rustls` server-side
+//! TLS stream before the same framing/decode pipeline as the plaintext
+//! path runs over them.
+use std::fs::File;
+use std::io::BufReader;
+use std::path::Path;
+use std::sync::Arc;
+
+use rustls::pki_types::{CertificateDer, PrivateKeyDer};
+use rustls::server::WebPkiClientVerifier;
+use rustls::{RootCertStore, ServerConfig};
+use rustls_pemfile::{certs, private_key};
+use tokio_rustls::TlsAcceptor;
+
+use crate::Args;
+
+/// Builds the broker's `TlsAcceptor` from `--tls-cert`/`--tls-key` (and,
+/// for mTLS, `--tls-client-ca`). Returns `None` unless both a certificate
+/// and a key are configured, so the broker falls back to a plaintext listener.
+pub fn build_acceptor(args: &Args) -> Option<TlsAcceptor> {
+    let (cert_path, key_path) = match (&args.tls_cert, &args.tls_key) {
+        (Some(cert), Some(key)) => (cert, key),
+        _ => return None,
+    };
+
+    let cert_chain = load_certs(cert_path);
+    let key = load_private_key(key_path);
+
+    let config_builder = ServerConfig::builder();
+    let config = match &args.tls_client_ca {
+        Some(ca_path) => {
+            let mut roots = RootCertStore::empty();
+            for cert in load_certs(ca_path) {
+                roots
+                    .add(cert)
+                    .expect("invalid certificate in tls-client-ca bundle");
+            }
+            let verifier = WebPkiClientVerifier::builder(Arc::new(roots))
+                .build()
+                .expect("invalid tls-client-ca trust root");
+            config_builder
+                .with_client_cert_verifier(verifier)
+                .with_single_cert(cert_chain, key)
+                .expect("invalid TLS certificate/key pair")
+        }
+        None => config_builder
+            .with_no_client_auth()
+            .with_single_cert(cert_chain, key)
+            .expect("invalid TLS certificate/key pair"),
+    };
+
+    Some(TlsAcceptor::from(Arc::new(config)))
+}
+
+fn load_certs(path: &Path) -> Vec<CertificateDer<'static>> {
+    let file = File::open(path).unwrap_or_else(|e| panic!("cannot open {}: {e}", path.display()));
+    certs(&mut BufReader::new(file))
+        .collect::<Result<Vec<_>, _>>()
+        .unwrap_or_else(|e| panic!("cannot parse certificate(s) in {}: {e}", path.display()))
+}
+
+fn load_private_key(path: &Path) -> PrivateKeyDer<'static> {
+    let file = File::open(path).unwrap_or_else(|e| panic!("cannot open {}: {e}", path.display()));
+    private_key(&mut BufReader::new(file))
+        .unwrap_or_else(|e| panic!("cannot parse private key in {}: {e}", path.display()))
+        .unwrap_or_else(|| panic!("no private key found in {}", path.display()))
+}
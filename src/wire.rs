@@ -0,0 +1,234 @@
+//! Wire-format primitives shared by the classic and flexible (KIP-482)
+//! encodings. Which one applies to a given header/body is decided by the
+//! caller based on the request's API key and version.
+use bytes::{Buf, BufMut};
+use thiserror::Error;
+
+/// A length or count read off the wire couldn't possibly be valid, because
+/// it claims more bytes than are actually left in the buffer. Surfaced
+/// instead of pre-allocating from the untrusted value directly — a forged
+/// multi-gigabyte length would otherwise make the allocator abort the
+/// whole process, which a per-connection `catch_unwind` can't catch.
+#[derive(Error, Debug)]
+#[error("declared length {declared} exceeds {remaining} remaining bytes")]
+pub struct LengthExceedsBuffer {
+    declared: usize,
+    remaining: usize,
+}
+
+fn check_length(declared: usize, buf: &impl Buf) -> Result<(), LengthExceedsBuffer> {
+    let remaining = buf.remaining();
+    if declared > remaining {
+        return Err(LengthExceedsBuffer { declared, remaining });
+    }
+    Ok(())
+}
+
+/// Reads a classic (non-flexible) nullable string: an `i16` length
+/// followed by that many bytes, with length `-1` meaning `None`. Used by
+/// both classic and flexible request headers — only the trailing tagged
+/// fields buffer differs between header versions. Errors rather than
+/// allocating if `len` claims more bytes than `buf` actually has left, the
+/// same guard `read_compact_string` and `read_tag_buffer` use.
+pub fn read_nullable_string(buf: &mut impl Buf) -> Result<Option<String>, LengthExceedsBuffer> {
+    let len = buf.get_i16();
+    if len < 0 {
+        return Ok(None);
+    }
+    let len = len as usize;
+    check_length(len, buf)?;
+    let mut bytes = vec![0u8; len];
+    buf.copy_to_slice(&mut bytes);
+    Ok(Some(String::from_utf8_lossy(&bytes).into_owned()))
+}
+
+/// Writes a classic nullable string in the same format `read_nullable_string` reads.
+pub fn write_nullable_string(buf: &mut impl BufMut, value: &Option<String>) {
+    match value {
+        Some(s) => {
+            buf.put_i16(s.len() as i16);
+            buf.put_slice(s.as_bytes());
+        }
+        None => buf.put_i16(-1),
+    }
+}
+
+/// Reads an unsigned varint: 7 bits per byte, little-endian, with the high
+/// bit of each byte set while more bytes follow.
+pub fn read_unsigned_varint(buf: &mut impl Buf) -> u32 {
+    let mut value = 0u32;
+    let mut shift = 0;
+    loop {
+        let byte = buf.get_u8();
+        value |= ((byte & 0x7F) as u32) << shift;
+        if byte & 0x80 == 0 {
+            break;
+        }
+        shift += 7;
+    }
+    value
+}
+
+/// Writes an unsigned varint in the format `read_unsigned_varint` reads.
+pub fn write_unsigned_varint(buf: &mut impl BufMut, mut value: u32) {
+    loop {
+        let mut byte = (value & 0x7F) as u8;
+        value >>= 7;
+        if value != 0 {
+            byte |= 0x80;
+        }
+        buf.put_u8(byte);
+        if value == 0 {
+            break;
+        }
+    }
+}
+
+/// Reads a COMPACT_STRING: length encoded as `unsigned_varint(len + 1)`,
+/// with `0` meaning null. Errors rather than allocating if `len` claims
+/// more bytes than `buf` actually has left.
+pub fn read_compact_string(buf: &mut impl Buf) -> Result<Option<String>, LengthExceedsBuffer> {
+    let len = read_unsigned_varint(buf);
+    if len == 0 {
+        return Ok(None);
+    }
+    let len = (len - 1) as usize;
+    check_length(len, buf)?;
+    let mut bytes = vec![0u8; len];
+    buf.copy_to_slice(&mut bytes);
+    Ok(Some(String::from_utf8_lossy(&bytes).into_owned()))
+}
+
+/// Writes a COMPACT_STRING in the format `read_compact_string` reads.
+pub fn write_compact_string(buf: &mut impl BufMut, value: &Option<String>) {
+    match value {
+        Some(s) => {
+            write_unsigned_varint(buf, s.len() as u32 + 1);
+            buf.put_slice(s.as_bytes());
+        }
+        None => write_unsigned_varint(buf, 0),
+    }
+}
+
+/// Reads a COMPACT_ARRAY length, which uses the same length+1 convention
+/// as COMPACT_STRING. Returns `None` for a null array.
+pub fn read_compact_array_len(buf: &mut impl Buf) -> Option<usize> {
+    let len = read_unsigned_varint(buf);
+    if len == 0 {
+        None
+    } else {
+        Some((len - 1) as usize)
+    }
+}
+
+/// Writes a COMPACT_ARRAY length in the format `read_compact_array_len` reads.
+pub fn write_compact_array_len(buf: &mut impl BufMut, len: usize) {
+    write_unsigned_varint(buf, len as u32 + 1);
+}
+
+/// Reads the tagged-fields buffer present at the end of every flexible
+/// struct: an unsigned-varint count followed by that many
+/// `(tag, size, bytes)` entries. The broker doesn't interpret any tags
+/// yet, so each entry's raw bytes are just preserved.
+///
+/// Every entry is at least 2 bytes on the wire (a tag varint and a size
+/// varint), so a `count` greater than the buffer's remaining bytes can
+/// never be satisfied; that case, and any entry whose `size` overruns what
+/// is left, errors instead of pre-allocating from the untrusted value.
+pub fn read_tag_buffer(buf: &mut impl Buf) -> Result<Vec<(u32, Vec<u8>)>, LengthExceedsBuffer> {
+    let count = read_unsigned_varint(buf) as usize;
+    check_length(count, buf)?;
+    let mut fields = Vec::with_capacity(count);
+    for _ in 0..count {
+        let tag = read_unsigned_varint(buf);
+        let size = read_unsigned_varint(buf) as usize;
+        check_length(size, buf)?;
+        let mut bytes = vec![0u8; size];
+        buf.copy_to_slice(&mut bytes);
+        fields.push((tag, bytes));
+    }
+    Ok(fields)
+}
+
+/// Writes a tagged-fields buffer in the format `read_tag_buffer` reads.
+pub fn write_tag_buffer(buf: &mut impl BufMut, fields: &[(u32, Vec<u8>)]) {
+    write_unsigned_varint(buf, fields.len() as u32);
+    for (tag, bytes) in fields {
+        write_unsigned_varint(buf, *tag);
+        write_unsigned_varint(buf, bytes.len() as u32);
+        buf.put_slice(bytes);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bytes::BytesMut;
+
+    #[test]
+    fn unsigned_varint_round_trips_single_and_multi_byte_values() {
+        for value in [0u32, 1, 127, 128, 16384, u32::MAX] {
+            let mut buf = BytesMut::new();
+            write_unsigned_varint(&mut buf, value);
+            let mut reader = &buf[..];
+            assert_eq!(read_unsigned_varint(&mut reader), value);
+        }
+    }
+
+    #[test]
+    fn nullable_string_rejects_length_past_end_of_buffer() {
+        let mut buf = BytesMut::new();
+        // Declares a 10-byte string but supplies none of its bytes.
+        buf.put_i16(10);
+        let mut reader = &buf[..];
+        assert!(read_nullable_string(&mut reader).is_err());
+    }
+
+    #[test]
+    fn compact_string_round_trips_value_and_null() {
+        let mut buf = BytesMut::new();
+        write_compact_string(&mut buf, &Some("console-producer".to_string()));
+        let mut reader = &buf[..];
+        assert_eq!(
+            read_compact_string(&mut reader).unwrap(),
+            Some("console-producer".to_string())
+        );
+
+        let mut buf = BytesMut::new();
+        write_compact_string(&mut buf, &None);
+        let mut reader = &buf[..];
+        assert_eq!(read_compact_string(&mut reader).unwrap(), None);
+    }
+
+    #[test]
+    fn compact_string_rejects_length_past_end_of_buffer() {
+        let mut buf = BytesMut::new();
+        // Declares a 10-byte string but supplies none of its bytes.
+        write_unsigned_varint(&mut buf, 11);
+        let mut reader = &buf[..];
+        assert!(read_compact_string(&mut reader).is_err());
+    }
+
+    #[test]
+    fn tag_buffer_round_trips_empty_and_populated() {
+        let mut buf = BytesMut::new();
+        write_tag_buffer(&mut buf, &[]);
+        let mut reader = &buf[..];
+        assert_eq!(read_tag_buffer(&mut reader).unwrap(), Vec::new());
+
+        let fields = vec![(1u32, vec![0xAA, 0xBB]), (5u32, vec![1, 2, 3])];
+        let mut buf = BytesMut::new();
+        write_tag_buffer(&mut buf, &fields);
+        let mut reader = &buf[..];
+        assert_eq!(read_tag_buffer(&mut reader).unwrap(), fields);
+    }
+
+    #[test]
+    fn tag_buffer_rejects_count_past_end_of_buffer() {
+        let mut buf = BytesMut::new();
+        // Declares a huge number of entries but supplies none of them.
+        write_unsigned_varint(&mut buf, u32::MAX);
+        let mut reader = &buf[..];
+        assert!(read_tag_buffer(&mut reader).is_err());
+    }
+}